@@ -0,0 +1,117 @@
+//! RT-safe logging for the audio callback.
+//!
+//! The callback must never allocate, lock, or format strings, so it only
+//! pushes plain `Copy` [`LogRecord`]s through the shared `rt_channel`
+//! ring buffer. A normal background thread drains it in a loop and does
+//! all the `println!`/formatting that used to happen in the callback
+//! itself.
+
+use crate::rt_channel::{self, DrainHandle, RtPusher};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+/// Capacity of the log ring buffer, in records.
+const LOG_RING_CAPACITY: usize = 4096;
+
+/// How often the drain thread polls the ring buffer when it's empty.
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// A single RT-safe log record pushed from the audio callback.
+///
+/// Kept `Copy` and free of any heap allocation so it can be written from
+/// the realtime thread without locking or formatting.
+#[derive(Debug, Clone, Copy)]
+pub struct LogRecord {
+    pub n: u64,
+    pub frames: u32,
+    pub samples: u32,
+    pub dt_secs: f64,
+    /// Callback→playback latency reported by `OutputCallbackInfo::timestamp()`.
+    pub latency_secs: f64,
+    /// Set when the gap since the previous callback exceeded 1.5x the
+    /// expected inter-callback period (frames / sample_rate).
+    pub is_underrun: bool,
+}
+
+/// The RT-side handle: push-only, never blocks or allocates.
+pub type RtLogger = RtPusher<LogRecord>;
+
+/// Non-RT handle to the drain thread, returned alongside the [`RtLogger`].
+pub struct LogHandle(DrainHandle<()>);
+
+impl LogHandle {
+    /// Signals the drain thread to stop, waits for it to drain the
+    /// remaining records, and reports any records the RT thread dropped.
+    pub fn stop_and_join(self) {
+        let (_, dropped) = self.0.stop_and_join("logger");
+        if dropped > 0 {
+            println!("[logger] {dropped} log record(s) dropped (RT thread outran the logger)");
+        }
+    }
+}
+
+/// Allocates the ring buffer, spawns the non-RT drain thread, and returns
+/// the RT-safe producer handle plus a handle to stop/join the drainer.
+///
+/// `log_every` controls how often the drain thread prints a per-record
+/// line (0 disables those lines, 1 prints every record). Buffer-size
+/// change warnings and the final latency summary are printed regardless.
+pub fn spawn(log_every: u64) -> (RtLogger, LogHandle) {
+    let (pusher, handle) = rt_channel::spawn(LOG_RING_CAPACITY, move |mut consumer, stop| {
+        let mut last_frames: u32 = 0;
+        let mut min_latency = f64::INFINITY;
+        let mut max_latency: f64 = 0.0;
+        let mut latency_sum: f64 = 0.0;
+        let mut latency_count: u64 = 0;
+        let mut underrun_count: u64 = 0;
+
+        loop {
+            while let Some(rec) = consumer.pop() {
+                if rec.frames != last_frames && last_frames != 0 {
+                    println!(
+                        "⚠️ buffer size changed: {} -> {} (frames per callback)",
+                        last_frames, rec.frames
+                    );
+                }
+                last_frames = rec.frames;
+
+                min_latency = min_latency.min(rec.latency_secs);
+                max_latency = max_latency.max(rec.latency_secs);
+                latency_sum += rec.latency_secs;
+                latency_count += 1;
+                if rec.is_underrun {
+                    underrun_count += 1;
+                }
+
+                if log_every > 0 && rec.n % log_every == 0 {
+                    println!(
+                        "[cb #{:>6}] frames/cb: {:>5} | samples: {:>5} | Δt={:.6}s | latency={:.3}ms{}",
+                        rec.n,
+                        rec.frames,
+                        rec.samples,
+                        rec.dt_secs,
+                        rec.latency_secs * 1000.0,
+                        if rec.is_underrun { " (under-run)" } else { "" },
+                    );
+                }
+            }
+
+            if stop.load(Ordering::Relaxed) {
+                break;
+            }
+            thread::sleep(DRAIN_POLL_INTERVAL);
+        }
+
+        if latency_count > 0 {
+            println!(
+                "[latency] min={:.3}ms avg={:.3}ms max={:.3}ms | under-runs: {underrun_count}",
+                min_latency * 1000.0,
+                (latency_sum / latency_count as f64) * 1000.0,
+                max_latency * 1000.0,
+            );
+        }
+    });
+
+    (pusher, LogHandle(handle))
+}
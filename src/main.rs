@@ -1,10 +1,19 @@
+mod logging;
+mod record;
+mod rt_channel;
+mod signal;
+
 use clap::Parser;
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use cpal::{
     BufferSize, Device, SampleFormat, SampleRate, StreamConfig, SupportedBufferSize,
     SupportedStreamConfig, SupportedStreamConfigRange,
 };
+use cpal::Sample;
+use logging::LogRecord;
+use signal::SignalKind;
 use std::fmt::Debug;
+use std::path::PathBuf;
 use std::thread;
 use std::time::Duration;
 
@@ -26,12 +35,67 @@ struct Args {
     /// Log every N callbacks (0=disable, 1=every time). Day1は非RTセーフなprintlnで観察する
     #[arg(long, default_value_t = 10)]
     log_every: u64,
+
+    /// How long to run the stream(s), in seconds
+    #[arg(long, default_value_t = 3)]
+    duration: u64,
+
+    /// Record the input device to this WAV path (enables full-duplex capture)
+    #[arg(long)]
+    record: Option<PathBuf>,
+
+    /// Input device name to record from (defaults to the system default input device)
+    #[arg(long)]
+    in_device: Option<String>,
+
+    /// Audio backend/host to use (e.g. alsa, jack, wasapi, asio). Defaults to the platform's default host.
+    #[arg(long)]
+    host: Option<String>,
+
+    /// Test signal to play on the output stream
+    #[arg(long, value_enum, default_value_t = SignalKind::Sine)]
+    signal: SignalKind,
+
+    /// Test signal frequency (Hz)
+    #[arg(long, default_value_t = 440.0)]
+    freq: f32,
+
+    /// Test signal amplitude (0.0..=1.0)
+    #[arg(long, default_value_t = 0.5)]
+    amplitude: f32,
+}
+
+/// Resolves `--host <name>` against `cpal::available_hosts()` and
+/// initializes it, or falls back to `cpal::default_host()` when no name
+/// was given. Fails with a clear message listing valid hosts on an
+/// unknown name.
+///
+/// No ASIO-specific handling is needed here: the device enumeration in
+/// `main` already calls `supported_output_configs()` on whichever host
+/// was selected, so the buffer-size ranges it prints are the ASIO
+/// driver's own reported limits once an ASIO host is chosen.
+fn select_host(name: Option<&str>) -> Result<cpal::Host, anyhow::Error> {
+    let Some(name) = name else {
+        return Ok(cpal::default_host());
+    };
+
+    let available = cpal::available_hosts();
+    let host_id = available
+        .iter()
+        .find(|id| id.name().eq_ignore_ascii_case(name))
+        .copied()
+        .ok_or_else(|| {
+            let names: Vec<_> = available.iter().map(|id| id.name()).collect();
+            anyhow::anyhow!("Unknown host {name:?}; valid hosts: {names:?}")
+        })?;
+
+    Ok(cpal::host_from_id(host_id)?)
 }
 
 fn main() -> Result<(), anyhow::Error> {
     let args = Args::parse();
 
-    let host = cpal::default_host();
+    let host = select_host(args.host.as_deref())?;
     let devices = host.devices()?;
     let default_out_device = host.default_output_device();
     let default_in_device = host.default_input_device();
@@ -113,33 +177,84 @@ fn main() -> Result<(), anyhow::Error> {
         "\nRequested: SR={} Hz, Channels={}, Buffer={} frames",
         args.sr, args.ch, args.buffer
     );
+
+    let out_device = default_out_device
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("No default output device"))?;
+
+    let sample_format = out_device.default_output_config()?.sample_format();
+
+    let (buffer_size, negotiated_range) =
+        negotiate_buffer_size(out_device, args.ch, args.sr, sample_format, args.buffer)?;
+
     let config = StreamConfig {
         channels: args.ch,
         sample_rate: SampleRate(args.sr),
-        buffer_size: BufferSize::Fixed(args.buffer),
+        buffer_size,
     };
 
-    let sample_format = default_out_device
-        .as_ref()
-        .ok_or_else(|| anyhow::anyhow!("No default output device"))?
-        .default_output_config()?
-        .sample_format();
+    let (rt_logger, log_handle) = logging::spawn(args.log_every);
+
+    let generator = signal::SignalGenerator::new(
+        args.signal,
+        args.freq,
+        args.amplitude,
+        args.sr as f32,
+        args.ch as usize,
+    );
 
     let stream = match sample_format {
-        SampleFormat::F32 => build_stream::<f32>(
-            default_out_device.as_ref().unwrap(),
-            &config,
-            args.log_every,
-        )?,
+        SampleFormat::I8 => build_stream::<i8>(out_device, &config, rt_logger, generator)?,
+        SampleFormat::I16 => build_stream::<i16>(out_device, &config, rt_logger, generator)?,
+        SampleFormat::I32 => build_stream::<i32>(out_device, &config, rt_logger, generator)?,
+        SampleFormat::I64 => build_stream::<i64>(out_device, &config, rt_logger, generator)?,
+        SampleFormat::U8 => build_stream::<u8>(out_device, &config, rt_logger, generator)?,
+        SampleFormat::U16 => build_stream::<u16>(out_device, &config, rt_logger, generator)?,
+        SampleFormat::U32 => build_stream::<u32>(out_device, &config, rt_logger, generator)?,
+        SampleFormat::U64 => build_stream::<u64>(out_device, &config, rt_logger, generator)?,
+        SampleFormat::F32 => build_stream::<f32>(out_device, &config, rt_logger, generator)?,
+        SampleFormat::F64 => build_stream::<f64>(out_device, &config, rt_logger, generator)?,
         other => anyhow::bail!("Unsupported sample format: {:?}", other),
     };
 
+    let capture = match args.record.as_ref() {
+        Some(path) => Some(start_capture(
+            &host,
+            default_in_device.as_ref(),
+            args.in_device.as_deref(),
+            path,
+        )?),
+        None => None,
+    };
+
     stream.play()?;
-    thread::sleep(Duration::from_secs(3));
-    println!("Stopped after 3s.");
+    thread::sleep(Duration::from_secs(args.duration));
+    drop(stream);
+    log_handle.stop_and_join();
 
+    if let Some((in_stream, capture_handle)) = capture {
+        drop(in_stream);
+        let (frames, dropped) = capture_handle.stop_and_join()?;
+        println!(
+            "Recorded {frames} frame(s) to {:?} ({dropped} sample(s) dropped)",
+            args.record.as_ref().unwrap()
+        );
+    }
+
+    println!("Stopped after {}s.", args.duration);
+
+    let buffer_report = match negotiated_range {
+        Some((min, max)) => format!(
+            "Buffer={:?} frames (supported range: {min}..={max})",
+            config.buffer_size
+        ),
+        None => format!(
+            "Buffer={:?} frames (supported range: unknown)",
+            config.buffer_size
+        ),
+    };
     println!(
-        "Actual   : SR={} Hz, Ch={} (Buffer/Latency=N/A; backend-dependent)",
+        "Actual   : SR={} Hz, Ch={} ({buffer_report}; see [latency] summary above)",
         config.sample_rate.0, config.channels,
     );
     Ok(())
@@ -166,48 +281,258 @@ fn match_config(range: &SupportedStreamConfigRange, def: &SupportedStreamConfig)
         && def.sample_rate().0 <= range.max_sample_rate().0
 }
 
+/// Negotiates the requested buffer size (in frames) against the device's
+/// supported range for the given channels/sample-rate/format, clamping
+/// and warning on mismatch. Falls back to `BufferSize::Default` when the
+/// matching config reports an unknown range. When no supported config
+/// matches, the requested size is passed through unclamped as
+/// `BufferSize::Fixed` with no range. Returns the chosen `BufferSize`
+/// plus the supported `min..=max` range, if known, for reporting.
+fn negotiate_buffer_size(
+    device: &Device,
+    channels: u16,
+    sample_rate: u32,
+    sample_format: SampleFormat,
+    requested: u32,
+) -> Result<(BufferSize, Option<(u32, u32)>), anyhow::Error> {
+    let matching = device.supported_output_configs()?.find(|cfg| {
+        cfg.channels() == channels
+            && cfg.sample_format() == sample_format
+            && cfg.min_sample_rate().0 <= sample_rate
+            && cfg.max_sample_rate().0 >= sample_rate
+    });
+
+    let Some(cfg) = matching else {
+        return Ok((BufferSize::Fixed(requested), None));
+    };
+
+    Ok(clamp_buffer_size(*cfg.buffer_size(), requested))
+}
+
+/// Pure clamping/fallback logic behind `negotiate_buffer_size`, split out
+/// so it can be exercised without a real `cpal::Device`. Clamps
+/// `requested` into `supported`'s range (warning on mismatch), or falls
+/// back to `BufferSize::Default` when the device doesn't report a usable
+/// range.
+fn clamp_buffer_size(
+    supported: SupportedBufferSize,
+    requested: u32,
+) -> (BufferSize, Option<(u32, u32)>) {
+    match supported {
+        SupportedBufferSize::Range { min, max } => {
+            let clamped = requested.clamp(min, max);
+            if clamped != requested {
+                println!(
+                    "Warning: requested buffer {requested} frames outside supported range {min}..={max}; clamping to {clamped}"
+                );
+            }
+            (BufferSize::Fixed(clamped), Some((min, max)))
+        }
+        SupportedBufferSize::Unknown => (BufferSize::Default, None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clamp_buffer_size_passes_through_in_range_request() {
+        let supported = SupportedBufferSize::Range { min: 64, max: 2048 };
+        assert_eq!(
+            clamp_buffer_size(supported, 512),
+            (BufferSize::Fixed(512), Some((64, 2048)))
+        );
+    }
+
+    #[test]
+    fn clamp_buffer_size_clamps_below_range() {
+        let supported = SupportedBufferSize::Range { min: 64, max: 2048 };
+        assert_eq!(
+            clamp_buffer_size(supported, 16),
+            (BufferSize::Fixed(64), Some((64, 2048)))
+        );
+    }
+
+    #[test]
+    fn clamp_buffer_size_clamps_above_range() {
+        let supported = SupportedBufferSize::Range { min: 64, max: 2048 };
+        assert_eq!(
+            clamp_buffer_size(supported, 8192),
+            (BufferSize::Fixed(2048), Some((64, 2048)))
+        );
+    }
+
+    #[test]
+    fn clamp_buffer_size_falls_back_to_default_when_unknown() {
+        assert_eq!(
+            clamp_buffer_size(SupportedBufferSize::Unknown, 512),
+            (BufferSize::Default, None)
+        );
+    }
+}
+
+/// Resolves the input device to record from, negotiates its default input
+/// config, opens the WAV writer, builds the input stream, and starts it
+/// playing. Runs concurrently with the output stream so the caller can do
+/// a loopback/monitor test.
+fn start_capture(
+    host: &cpal::Host,
+    default_in_device: Option<&Device>,
+    in_device_name: Option<&str>,
+    path: &std::path::Path,
+) -> Result<(cpal::Stream, record::CaptureHandle), anyhow::Error> {
+    let in_device = match in_device_name {
+        Some(name) => host
+            .input_devices()?
+            .find(|d| d.name().map(|n| n == name).unwrap_or(false))
+            .ok_or_else(|| anyhow::anyhow!("No input device named {name:?}"))?,
+        None => default_in_device
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("No default input device"))?,
+    };
+
+    let in_cfg = in_device.default_input_config()?;
+    let in_sample_format = in_cfg.sample_format();
+    let in_stream_config = in_cfg.config();
+
+    println!(
+        "Recording from {:?}: {} Hz, {} ch, format {:?} -> {path:?}",
+        in_device.name().unwrap_or_else(|_| "<unknown>".into()),
+        in_stream_config.sample_rate.0,
+        in_stream_config.channels,
+        in_sample_format,
+    );
+
+    let (rt_capture, capture_handle) = record::spawn(
+        path,
+        in_stream_config.channels,
+        in_stream_config.sample_rate.0,
+    )?;
+
+    let in_stream = match in_sample_format {
+        SampleFormat::I8 => build_input_stream::<i8>(&in_device, &in_stream_config, rt_capture)?,
+        SampleFormat::I16 => {
+            build_input_stream::<i16>(&in_device, &in_stream_config, rt_capture)?
+        }
+        SampleFormat::I32 => {
+            build_input_stream::<i32>(&in_device, &in_stream_config, rt_capture)?
+        }
+        SampleFormat::I64 => {
+            build_input_stream::<i64>(&in_device, &in_stream_config, rt_capture)?
+        }
+        SampleFormat::U8 => build_input_stream::<u8>(&in_device, &in_stream_config, rt_capture)?,
+        SampleFormat::U16 => {
+            build_input_stream::<u16>(&in_device, &in_stream_config, rt_capture)?
+        }
+        SampleFormat::U32 => {
+            build_input_stream::<u32>(&in_device, &in_stream_config, rt_capture)?
+        }
+        SampleFormat::U64 => {
+            build_input_stream::<u64>(&in_device, &in_stream_config, rt_capture)?
+        }
+        SampleFormat::F32 => {
+            build_input_stream::<f32>(&in_device, &in_stream_config, rt_capture)?
+        }
+        SampleFormat::F64 => {
+            build_input_stream::<f64>(&in_device, &in_stream_config, rt_capture)?
+        }
+        other => anyhow::bail!("Unsupported input sample format: {:?}", other),
+    };
+
+    in_stream.play()?;
+    Ok((in_stream, capture_handle))
+}
+
+/// RT-safe input callback: converts each sample to `f32` (the format the
+/// WAV writer thread encodes) and pushes it into the capture ring buffer,
+/// without allocating.
+fn build_input_stream<T>(
+    device: &Device,
+    config: &StreamConfig,
+    mut rt_capture: record::RtCapture,
+) -> Result<cpal::Stream, anyhow::Error>
+where
+    T: cpal::SizedSample,
+    f32: cpal::FromSample<T>,
+{
+    let err_fn = |e| eprintln!("[stream error] {e}");
+
+    let stream = device.build_input_stream(
+        config,
+        move |data: &[T], _info| {
+            for &sample in data {
+                rt_capture.push(f32::from_sample(sample));
+            }
+        },
+        err_fn,
+        None,
+    )?;
+    Ok(stream)
+}
+
 fn build_stream<T>(
     device: &Device,
     config: &StreamConfig,
-    log_every: u64,
+    mut rt_logger: logging::RtLogger,
+    mut generator: signal::SignalGenerator,
 ) -> Result<cpal::Stream, anyhow::Error>
 where
-    T: cpal::SizedSample + num_traits::Zero,
+    T: cpal::SizedSample,
+    T: cpal::FromSample<f32>,
 {
     let err_fn = |e| eprintln!("[stream error] {e}");
-    let mut last_time = std::time::Instant::now();
-    let mut last_len: usize = 0;
+    let mut last_callback_instant: Option<cpal::StreamInstant> = None;
     let mut n: u64 = 0;
 
     let channels = config.channels;
+    let sample_rate = config.sample_rate.0 as f64;
 
     let stream = device.build_output_stream(
         config,
-        move |data: &mut [T], _info| {
-            if log_every > 0 {
-                n += 1;
-                let now = std::time::Instant::now();
-                let dt = now.duration_since(last_time).as_secs_f64();
-                last_time = now;
-
-                let len = data.len();
-                if len != last_len && last_len != 0 {
-                    println!(
-                        "⚠️ buffer size changed: {} -> {} (frames per callback)",
-                        last_len, len
-                    );
-                }
-                last_len = len;
-
-                if n % log_every == 0 {
-                    let ch = channels as usize;
-                    let frames = if ch > 0 { len / ch } else { len };
-                    println!(
-                        "[cb #{:>6}] frames/cb: {:>5} | samples: {:>5} | Δt={:.6}s",
-                        n, frames, len, dt
-                    );
+        move |data: &mut [T], info: &cpal::OutputCallbackInfo| {
+            n += 1;
+
+            let len = data.len();
+            let ch = channels as usize;
+            let frames = if ch > 0 { len / ch } else { len };
+
+            for frame in data.chunks_mut(ch.max(1)) {
+                for (channel, slot) in frame.iter_mut().enumerate() {
+                    *slot = T::from_sample(generator.next_sample(channel));
                 }
             }
+
+            // `timestamp.callback` is a `cpal::StreamInstant` CPAL already
+            // computes for us, so diffing consecutive values gives us the
+            // inter-callback gap without a syscall in the hot path (unlike
+            // `std::time::Instant::now()`). Reuse it for both dt and the
+            // under-run check instead of timing the callback separately.
+            let timestamp = info.timestamp();
+            let gap_secs = last_callback_instant
+                .and_then(|last| timestamp.callback.duration_since(&last))
+                .map(|d| d.as_secs_f64());
+            last_callback_instant = Some(timestamp.callback);
+
+            let latency_secs = timestamp
+                .playback
+                .duration_since(&timestamp.callback)
+                .map(|d| d.as_secs_f64())
+                .unwrap_or(0.0);
+
+            let expected_period = frames as f64 / sample_rate;
+            let is_underrun = gap_secs
+                .map(|gap| gap > 1.5 * expected_period)
+                .unwrap_or(false);
+
+            rt_logger.push(LogRecord {
+                n,
+                frames: frames as u32,
+                samples: len as u32,
+                dt_secs: gap_secs.unwrap_or(0.0),
+                latency_secs,
+                is_underrun,
+            });
         },
         err_fn,
         None,
@@ -0,0 +1,88 @@
+//! Input capture + WAV recording.
+//!
+//! Built on the same `rt_channel` push/drain plumbing as `logging`: the
+//! RT input callback only pushes `f32` samples (converted via
+//! `cpal::FromSample`, the same conversion CPAL's own `record_wav`
+//! example uses), never allocating or formatting. A background writer
+//! thread drains the ring buffer and encodes the samples to a WAV file
+//! with `hound`.
+
+use crate::rt_channel::{self, DrainHandle, RtPusher};
+use hound::{SampleFormat, WavSpec, WavWriter};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+/// Capacity of the capture ring buffer, in samples (not frames).
+const CAPTURE_RING_CAPACITY: usize = 1 << 16;
+
+/// How often the writer thread polls the ring buffer when it's empty.
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// The RT-side handle: push-only, never blocks or allocates.
+pub type RtCapture = RtPusher<f32>;
+
+/// Non-RT handle to the writer thread, returned alongside the [`RtCapture`].
+pub struct CaptureHandle {
+    inner: DrainHandle<anyhow::Result<u64>>,
+    channels: u16,
+}
+
+impl CaptureHandle {
+    /// Signals the writer thread to stop, waits for the WAV file to be
+    /// finalized, and returns `(frames_written, samples_dropped)`. If the
+    /// writer thread panicked, reports it instead of aborting the run.
+    pub fn stop_and_join(self) -> anyhow::Result<(u64, u64)> {
+        let (result, dropped) = self.inner.stop_and_join("recorder");
+        let samples_written = match result {
+            Some(r) => r?,
+            None => 0,
+        };
+        let frames = samples_written / self.channels.max(1) as u64;
+        Ok((frames, dropped))
+    }
+}
+
+/// Creates `path` as a WAV file and spawns the writer thread, returning
+/// the RT-safe capture handle plus a handle to stop/join the writer.
+///
+/// Samples are always written as 32-bit float regardless of the
+/// negotiated input sample format — the RT callback converts every
+/// format to `f32` before pushing (see `build_input_stream` in
+/// `main.rs`), so that's what ends up on disk. Only `channels` and
+/// `sample_rate` come from the negotiated input config.
+pub fn spawn(
+    path: &Path,
+    channels: u16,
+    sample_rate: u32,
+) -> anyhow::Result<(RtCapture, CaptureHandle)> {
+    let spec = WavSpec {
+        channels,
+        sample_rate,
+        bits_per_sample: 32,
+        sample_format: SampleFormat::Float,
+    };
+    let mut writer: WavWriter<BufWriter<File>> = WavWriter::create(path, spec)?;
+
+    let (pusher, inner) = rt_channel::spawn(CAPTURE_RING_CAPACITY, move |mut consumer, stop| {
+        let mut samples_written: u64 = 0;
+        loop {
+            while let Some(sample) = consumer.pop() {
+                writer.write_sample(sample)?;
+                samples_written += 1;
+            }
+
+            if stop.load(Ordering::Relaxed) {
+                break;
+            }
+            thread::sleep(DRAIN_POLL_INTERVAL);
+        }
+        writer.finalize()?;
+        Ok(samples_written)
+    });
+
+    Ok((pusher, CaptureHandle { inner, channels }))
+}
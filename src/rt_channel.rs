@@ -0,0 +1,88 @@
+//! Shared RT-safe push/drain plumbing used by both `logging` and `record`.
+//!
+//! Both modules need the same shape: an RT-side handle that pushes items
+//! into a bounded lock-free SPSC ring buffer without ever blocking or
+//! allocating (counting drops instead of blocking when full), and a
+//! non-RT handle that stops a drain thread, joins it, and reports a
+//! panic instead of aborting the run or silently swallowing it.
+
+use ringbuf::{Consumer, HeapRb, Producer};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+/// The RT-side handle: push-only, never blocks or allocates.
+///
+/// On a full ring buffer the item is dropped and the drop count is
+/// bumped instead, so the caller never stalls waiting on the drain
+/// thread.
+pub struct RtPusher<T> {
+    producer: Producer<T, Arc<HeapRb<T>>>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl<T> RtPusher<T> {
+    /// Pushes an item; on a full buffer, counts the drop instead of blocking.
+    #[inline]
+    pub fn push(&mut self, item: T) {
+        if self.producer.push(item).is_err() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Non-RT handle to the drain thread, returned alongside an [`RtPusher`].
+pub struct DrainHandle<R> {
+    stop: Arc<AtomicBool>,
+    dropped: Arc<AtomicU64>,
+    thread: thread::JoinHandle<R>,
+}
+
+impl<R> DrainHandle<R> {
+    /// Signals the drain thread to stop, joins it, and returns its result
+    /// (or `None` if it panicked, which is reported via `println!` rather
+    /// than propagated as an abort) plus the number of items the RT side
+    /// dropped.
+    pub fn stop_and_join(self, panic_label: &str) -> (Option<R>, u64) {
+        self.stop.store(true, Ordering::Relaxed);
+        let result = match self.thread.join() {
+            Ok(r) => Some(r),
+            Err(_) => {
+                println!("[{panic_label}] drain thread panicked; its result may be incomplete");
+                None
+            }
+        };
+        (result, self.dropped.load(Ordering::Relaxed))
+    }
+}
+
+/// Allocates a ring buffer of `capacity` items and spawns `drain` as the
+/// background thread, handing it the `Consumer` and the stop flag it
+/// should check once it has run the buffer dry. Returns the RT-safe
+/// pusher plus a handle to stop/join the drain thread.
+pub fn spawn<T, R, F>(capacity: usize, drain: F) -> (RtPusher<T>, DrainHandle<R>)
+where
+    T: Send + 'static,
+    R: Send + 'static,
+    F: FnOnce(Consumer<T, Arc<HeapRb<T>>>, Arc<AtomicBool>) -> R + Send + 'static,
+{
+    let ring = HeapRb::<T>::new(capacity);
+    let (producer, consumer) = ring.split();
+    let dropped = Arc::new(AtomicU64::new(0));
+    let stop = Arc::new(AtomicBool::new(false));
+    let stop_for_thread = Arc::clone(&stop);
+
+    let thread = thread::spawn(move || drain(consumer, stop_for_thread));
+
+    (
+        RtPusher {
+            producer,
+            dropped: Arc::clone(&dropped),
+        },
+        DrainHandle {
+            stop,
+            dropped,
+            thread,
+        },
+    )
+}